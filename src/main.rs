@@ -1,20 +1,30 @@
 use core::panic;
-use std::{env, fs::create_dir_all, path::PathBuf};
+use std::{
+    cmp::Reverse,
+    env,
+    fs::{create_dir_all, File},
+    io::{self, Write},
+    path::PathBuf,
+};
 
 use chrono::{DateTime, Duration, Utc};
-use clap::{arg, Command};
+use chrono_tz::Tz;
+use clap::{arg, ArgMatches, Command};
 use humantime::format_duration;
+use serde::{Deserialize, Serialize};
 use shellexpand;
 use sqlite::{Connection, State};
 use tabled::{builder::Builder, settings::Style};
 
 // A mission is a task with a name, start_date and end_date
 // A mission is considered `ongoing` whenever it has no `end_date`
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 struct Mission {
     name: String,
     start_date: DateTime<Utc>,
     end_date: Option<DateTime<Utc>>,
+    note: Option<String>,
+    project: Option<String>,
 }
 
 impl Mission {
@@ -23,29 +33,104 @@ impl Mission {
             name,
             start_date,
             end_date: None,
+            note: None,
+            project: None,
         }
     }
 
     fn elapsed_time(&self) -> Duration {
-        let duration = match self.end_date {
+        match self.end_date {
             Some(end_date) => end_date - self.start_date,
             None => Utc::now() - self.start_date,
-        };
-        Duration::seconds(duration.num_seconds())
+        }
     }
 }
 
 // Get the path to the db file
 const DEFAULT_DB_PATH: &str = "~/.local/share/tempo/tempo.db";
 
-// TODO: Add export command to generate a CSV of the data range
+// Get the path to the config file
+const DEFAULT_CONFIG_PATH: &str = "~/.config/tempo/config.toml";
+
+const DEFAULT_DATE_FORMAT: &str = "%d/%m/%Y %H:%M:%S";
+
+// User-provided settings loaded from the TOML config file
+#[derive(Debug, Default, Deserialize)]
+struct Config {
+    db_path: Option<String>,
+    date_format: Option<String>,
+    timezone: Option<String>,
+}
+
+impl Config {
+    fn timezone(&self) -> Tz {
+        self.timezone
+            .as_deref()
+            .and_then(|tz| tz.parse().ok())
+            .unwrap_or(chrono_tz::UTC)
+    }
+
+    fn date_format(&self) -> &str {
+        self.date_format.as_deref().unwrap_or(DEFAULT_DATE_FORMAT)
+    }
+
+    // Converts a UTC date to the configured display timezone and format
+    fn format_date(&self, date: DateTime<Utc>) -> String {
+        date.with_timezone(&self.timezone())
+            .format(self.date_format())
+            .to_string()
+    }
+}
+
+// Get the path to the config file, from `TEMPO_CONFIG` or the default one
+fn get_config_path() -> String {
+    match env::var("TEMPO_CONFIG") {
+        Ok(value) => value,
+        Err(_) => shellexpand::full(DEFAULT_CONFIG_PATH).unwrap().to_string(),
+    }
+}
+
+// Load the config file if it exists, falling back to defaults otherwise
+fn load_config() -> Config {
+    let path = get_config_path();
+
+    match std::fs::read_to_string(&path) {
+        Ok(contents) => toml::from_str(&contents).expect("Failed to parse the config file"),
+        Err(_) => Config::default(),
+    }
+}
+
+// Create the missions table if it does not exist yet, and migrate older
+// databases that predate the `note` and `project` columns
+fn ensure_schema(db: &Connection) {
+    db.execute("CREATE TABLE IF NOT EXISTS missions (id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL, start_date TEXT NOT NULL, end_date TEXT, note TEXT, project TEXT)").unwrap();
+
+    for column in ["note", "project"] {
+        let has_column = db
+            .prepare(format!(
+                "SELECT 1 FROM pragma_table_info('missions') WHERE name = '{}'",
+                column
+            ))
+            .unwrap()
+            .into_iter()
+            .count()
+            > 0;
+
+        if !has_column {
+            db.execute(format!("ALTER TABLE missions ADD COLUMN {} TEXT", column))
+                .unwrap();
+        }
+    }
+}
+
 fn main() {
-    ensure_db_path();
+    let config = load_config();
 
-    let db = Connection::open(get_db_path()).expect("Failed to open the database");
+    ensure_db_path(&config);
 
-    // Create table if it does not exist
-    db.execute("CREATE TABLE IF NOT EXISTS missions (id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL, start_date TEXT NOT NULL, end_date TEXT)").unwrap();
+    let db = Connection::open(get_db_path(&config)).expect("Failed to open the database");
+
+    ensure_schema(&db);
 
     let cmd = Command::new("tempo")
         .about("Personal time tracking utility")
@@ -55,17 +140,63 @@ fn main() {
             Command::new("start")
                 .about("Start a new mission")
                 .arg(arg!(<NAME> "The name of the mission"))
+                .arg(arg!(--project <NAME> "The project this mission belongs to"))
                 .arg_required_else_help(true),
         )
         .subcommand(Command::new("status").about("Show the current mission status"))
         .subcommand(Command::new("stop").about("Stop all ongoing missions"))
         .subcommand(Command::new("resume").about("Resume the latest stopped mission"))
+        .subcommand(
+            Command::new("edit")
+                .about("Amend a past mission")
+                .arg(arg!(--id <ID> "The id of the mission to edit, defaults to the most recent one"))
+                .arg(arg!(--start <TIME> "The new start date"))
+                .arg(arg!(--end <TIME> "The new end date"))
+                .arg(arg!(--name <NAME> "The new name"))
+                .arg(arg!(--note <TEXT> "A note attached to the mission")),
+        )
         .subcommand(
             Command::new("ls")
                 .about("List the missions")
                 .arg(arg!(--from <FROM> "The start of the selection date range"))
+                .arg(arg!(--to <TO> "The end of the selection date range"))
+                .arg(arg!(--project <NAME> "Only show missions for this project"))
+                .arg(arg!(--tag <NAME> "Only show missions tagged with this project"))
+                .arg(
+                    arg!(--sort <BY> "Sort missions by name, duration or start date")
+                        .value_parser(["name", "duration", "start"]),
+                )
                 .arg_required_else_help(false),
         )
+        .subcommand(
+            Command::new("report")
+                .about("Show a time report summed across missions")
+                .arg(arg!(--from <FROM> "The start of the selection date range"))
+                .arg(arg!(--to <TO> "The end of the selection date range"))
+                .arg(
+                    arg!(--by <BY> "Group the report by mission name or by day")
+                        .value_parser(["name", "day"])
+                        .default_value("name"),
+                )
+                .arg(arg!(--project <NAME> "Only include missions for this project"))
+                .arg(arg!(--tag <NAME> "Only include missions tagged with this project"))
+                .arg(
+                    arg!(--sort <BY> "Sort groups by name, duration or start date")
+                        .value_parser(["name", "duration", "start"]),
+                ),
+        )
+        .subcommand(
+            Command::new("export")
+                .about("Export missions to CSV or JSON")
+                .arg(
+                    arg!(--format <FORMAT> "The output format")
+                        .value_parser(["csv", "json"])
+                        .required(true),
+                )
+                .arg(arg!(--from <FROM> "The start of the selection date range"))
+                .arg(arg!(--to <TO> "The end of the selection date range"))
+                .arg(arg!(--output <PATH> "Write the export to this file instead of stdout")),
+        )
         .subcommand(Command::new("info").about("Print system information"));
 
     let matches = cmd.get_matches();
@@ -73,7 +204,12 @@ fn main() {
     match matches.subcommand() {
         Some(("start", arg_matches)) => {
             if let Some(name) = arg_matches.get_one::<String>("NAME") {
-                let mission = start_new_mission(&name, &db);
+                let (name, tag) = parse_name_tag(name);
+                if name.is_empty() {
+                    panic!("The mission name must not be empty");
+                }
+                let project = arg_matches.get_one::<String>("project").cloned().or(tag);
+                let mission = start_new_mission(&name, project, &db);
                 println!("New mission started: {}", mission.name);
             }
         }
@@ -88,55 +224,104 @@ fn main() {
             resume_latest_mission(&db);
             println!("Last mission has been resumed if there was any");
         }
+        Some(("edit", arg_matches)) => {
+            edit_mission(&db, arg_matches);
+        }
         Some(("ls", arg_matches)) => {
+            let to = arg_matches.get_one::<String>("to");
+            let project = arg_matches
+                .get_one::<String>("project")
+                .or_else(|| arg_matches.get_one::<String>("tag"))
+                .map(|s| s.as_str());
+            let sort = arg_matches.get_one::<String>("sort").map(|s| s.as_str());
             if let Some(from) = arg_matches.get_one::<String>("from") {
-                print_report(&db, &from);
+                print_report(&db, &config, from, to, project, sort);
             } else {
-                list_missions(&db);
+                list_missions(&db, &config, to.map(|s| s.as_str()), project, sort);
             }
         }
+        Some(("report", arg_matches)) => {
+            let from = arg_matches.get_one::<String>("from");
+            let to = arg_matches.get_one::<String>("to");
+            let by = arg_matches.get_one::<String>("by").unwrap();
+            let project = arg_matches
+                .get_one::<String>("project")
+                .or_else(|| arg_matches.get_one::<String>("tag"))
+                .map(|s| s.as_str());
+            let sort = arg_matches.get_one::<String>("sort").map(|s| s.as_str());
+            print_aggregated_report(&db, from, to, by, project, sort);
+        }
+        Some(("export", arg_matches)) => {
+            let format = arg_matches.get_one::<String>("format").unwrap();
+            let from = arg_matches.get_one::<String>("from");
+            let to = arg_matches.get_one::<String>("to");
+            let output = arg_matches.get_one::<String>("output");
+            export_missions(&db, format, from, to, output);
+        }
         Some(("info", _)) => {
-            print_info(&db);
+            print_info(&db, &config);
         }
         _ => unreachable!(),
     }
 }
 
-// Get the path to the DB from env or the default one
-fn get_db_path() -> String {
-    match env::var("TEMPO_DB_PATH") {
-        Ok(value) => value,
-        Err(_) => shellexpand::full(DEFAULT_DB_PATH).unwrap().to_string(),
+// Get the path to the DB from the config file, env or the default one
+fn get_db_path(config: &Config) -> String {
+    match &config.db_path {
+        Some(db_path) => shellexpand::full(db_path).unwrap().to_string(),
+        None => match env::var("TEMPO_DB_PATH") {
+            Ok(value) => value,
+            Err(_) => shellexpand::full(DEFAULT_DB_PATH).unwrap().to_string(),
+        },
     }
 }
 
 // Make sure that the path to the db file exists
-fn ensure_db_path() {
-    let path = PathBuf::from(get_db_path());
+fn ensure_db_path(config: &Config) {
+    let path = PathBuf::from(get_db_path(config));
     let dir = path.parent().unwrap();
     create_dir_all(dir).unwrap();
 }
 
 // Starts a new mission
 // Stops all the active missions before hand so theres anly one running
-fn start_new_mission(name: &String, db: &Connection) -> Mission {
+fn start_new_mission(name: &str, project: Option<String>, db: &Connection) -> Mission {
     stop_active_missions(&db);
 
-    let mission = Mission::new(name.to_string(), Utc::now());
+    let mut mission = Mission::new(name.to_string(), Utc::now());
+    mission.project = project;
 
     let mut stmt = db
-        .prepare("INSERT INTO missions (name, start_date) VALUES (:name, :start_date)")
+        .prepare("INSERT INTO missions (name, start_date, project) VALUES (:name, :start_date, :project)")
         .unwrap();
 
     stmt.bind((":name", mission.name.as_str())).unwrap();
     stmt.bind((":start_date", mission.start_date.to_rfc3339().as_str()))
         .unwrap();
+    stmt.bind((":project", mission.project.as_deref())).unwrap();
 
     stmt.next().expect("Failed to insert mission into db");
 
     mission
 }
 
+// Extracts an "@tag" token anywhere in a mission name, e.g. "Refactor @client-x"
+// becomes ("Refactor", Some("client-x"))
+fn parse_name_tag(name: &str) -> (String, Option<String>) {
+    let mut words: Vec<&str> = name.split_whitespace().collect();
+
+    match words
+        .iter()
+        .position(|word| word.len() > 1 && word.starts_with('@'))
+    {
+        Some(index) => {
+            let tag = words.remove(index)[1..].to_string();
+            (words.join(" "), Some(tag))
+        }
+        None => (name.to_string(), None),
+    }
+}
+
 // Prints out the latest active mission
 fn print_status(db: &Connection) {
     let mut stmt = db
@@ -153,7 +338,7 @@ fn print_status(db: &Connection) {
         println!(
             "{} ({})",
             mission.name,
-            format_duration(mission.elapsed_time().to_std().unwrap())
+            format_elapsed(mission.elapsed_time())
         );
     } else {
         println!("No active mission");
@@ -172,13 +357,46 @@ fn stop_active_missions(db: &Connection) {
 }
 
 // TODO: Add an option "-n" to limit the rows
-fn list_missions(db: &Connection) {
-    let mut stmt = db
-        .prepare("SELECT * from missions ORDER BY id DESC LIMIT 10")
-        .unwrap();
+// Loads missions matching the optional date range, ordered most recent first.
+// Shared by `ls`, `report` and `export` so there is a single query path.
+fn fetch_missions(
+    db: &Connection,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    project: Option<&str>,
+) -> Vec<Mission> {
+    let mut conditions = Vec::new();
+    if from.is_some() {
+        conditions.push("(start_date >= :start_date OR end_date IS NULL)");
+    }
+    if to.is_some() {
+        conditions.push("start_date <= :end_date");
+    }
+    if project.is_some() {
+        conditions.push("project = :project");
+    }
 
-    let mut builder = Builder::default();
-    builder.set_header(["", "Name", "Started At", "Ended At", "Duration"]);
+    let mut query = "SELECT * FROM missions".to_string();
+    if !conditions.is_empty() {
+        query.push_str(" WHERE ");
+        query.push_str(&conditions.join(" AND "));
+    }
+    query.push_str(" ORDER BY start_date DESC");
+
+    let mut stmt = db.prepare(query).unwrap();
+
+    if let Some(from) = from {
+        stmt.bind((":start_date", from.to_rfc3339().as_str()))
+            .unwrap();
+    }
+    if let Some(to) = to {
+        stmt.bind((":end_date", to.to_rfc3339().as_str())).unwrap();
+    }
+    if let Some(project) = project {
+        stmt.bind((":project", project)).unwrap();
+    }
+
+    let mut missions = Vec::new();
 
     while let Ok(State::Row) = stmt.next() {
         let name = stmt.read::<String, _>("name").unwrap();
@@ -199,24 +417,67 @@ fn list_missions(db: &Connection) {
 
         let mut mission = Mission::new(name, start_date);
         mission.end_date = end_date;
+        mission.note = stmt.read::<Option<String>, _>("note").unwrap();
+        mission.project = stmt.read::<Option<String>, _>("project").unwrap();
+
+        missions.push(mission);
+    }
+
+    missions
+}
+
+// Sorts missions in place by the requested key; unrecognized/absent keys
+// leave the existing start_date-descending order from `fetch_missions`
+fn sort_missions(missions: &mut [Mission], sort: Option<&str>) {
+    match sort {
+        Some("name") => missions.sort_by_key(|m| m.name.clone()),
+        Some("duration") => missions.sort_by_key(|m| Reverse(m.elapsed_time())),
+        Some("start") => missions.sort_by_key(|m| Reverse(m.start_date)),
+        _ => {}
+    }
+}
 
+// TODO: Add an option "-n" to limit the rows
+fn list_missions(
+    db: &Connection,
+    config: &Config,
+    to: Option<&str>,
+    project: Option<&str>,
+    sort: Option<&str>,
+) {
+    let to_date = to.map(parse_time);
+    let mut missions = fetch_missions(db, None, to_date, project);
+    sort_missions(&mut missions, sort);
+
+    let mut builder = Builder::default();
+    builder.set_header([
+        "",
+        "Name",
+        "Project",
+        "Started At",
+        "Ended At",
+        "Duration",
+        "Note",
+    ]);
+
+    for mission in missions.iter().take(10) {
         let formatted_end_date = match mission.end_date {
-            Some(date) => date.format("%d/%m/%Y %H:%M:%S").to_string(),
+            Some(date) => config.format_date(date),
             None => String::new(),
         };
 
         builder.push_record([
-            if end_date.is_none() { "⏺" } else { "" },
+            if mission.end_date.is_none() {
+                "⏺"
+            } else {
+                ""
+            },
             mission.name.as_str(),
-            mission
-                .start_date
-                .format("%d/%m/%Y %H:%M:%S")
-                .to_string()
-                .as_str(),
+            mission.project.as_deref().unwrap_or(""),
+            config.format_date(mission.start_date).as_str(),
             formatted_end_date.as_str(),
-            format_duration(mission.elapsed_time().to_std().unwrap())
-                .to_string()
-                .as_str(),
+            format_elapsed(mission.elapsed_time()).as_str(),
+            mission.note.as_deref().unwrap_or(""),
         ]);
     }
 
@@ -226,6 +487,154 @@ fn list_missions(db: &Connection) {
     println!("{}", table);
 }
 
+// Renders a duration with at most two units: hours and minutes (minutes left out
+// when zero), else minutes and seconds, else seconds with milliseconds when
+// non-zero, e.g. "2h15m", "3h", "5m9s" or "47.120s"
+fn format_elapsed(d: Duration) -> String {
+    let s = d.num_seconds();
+    let ms = d.num_milliseconds();
+
+    let hours = s / 3600;
+    let minutes = (s / 60) % 60;
+    let seconds = s % 60;
+    let millis = ms % 1000;
+
+    if hours != 0 {
+        if minutes != 0 {
+            format!("{}h{}m", hours, minutes)
+        } else {
+            format!("{}h", hours)
+        }
+    } else if minutes != 0 {
+        format!("{}m{}s", minutes, seconds)
+    } else if millis != 0 {
+        format!("{}.{:03}s", seconds, millis)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+// Parse a human date expression (e.g. "yesterday 9am") relative to now
+fn parse_time(value: &str) -> DateTime<Utc> {
+    let now = Utc::now().timestamp();
+    let tz = timelib::Timezone::parse("UTC").unwrap();
+    let ts = timelib::strtotime(value, Some(now), &tz).unwrap();
+    DateTime::from_timestamp(ts, 0).expect("Failed to parse the provided date")
+}
+
+// Amends a past mission: start/end date, name and note can all be changed
+fn edit_mission(db: &Connection, arg_matches: &ArgMatches) {
+    let id: i64 = match arg_matches.get_one::<String>("id") {
+        Some(id) => id.parse().expect("The id must be a number"),
+        None => {
+            let mut stmt = db
+                .prepare("SELECT id FROM missions ORDER BY start_date DESC LIMIT 1")
+                .unwrap();
+
+            match stmt.next() {
+                Ok(State::Row) => stmt.read::<i64, _>("id").unwrap(),
+                _ => panic!("There is no mission to edit"),
+            }
+        }
+    };
+
+    let mut stmt = db.prepare("SELECT * FROM missions WHERE id = :id").unwrap();
+    stmt.bind((":id", id)).unwrap();
+
+    if stmt.next().unwrap() != State::Row {
+        panic!("No mission found with id {}", id);
+    }
+
+    let name = match arg_matches.get_one::<String>("name") {
+        Some(name) => {
+            if name.is_empty() {
+                panic!("The mission name must not be empty");
+            }
+            name.to_string()
+        }
+        None => stmt.read::<String, _>("name").unwrap(),
+    };
+
+    let schedule_changed = arg_matches.contains_id("start") || arg_matches.contains_id("end");
+
+    let start_date = match arg_matches.get_one::<String>("start") {
+        Some(start) => parse_time(start),
+        None => {
+            DateTime::parse_from_rfc3339(stmt.read::<String, _>("start_date").unwrap().as_str())
+                .unwrap()
+                .with_timezone(&Utc)
+        }
+    };
+
+    let end_date = match arg_matches.get_one::<String>("end") {
+        Some(end) => Some(parse_time(end)),
+        None => match stmt.read::<Option<String>, _>("end_date").unwrap() {
+            Some(end_date_str) => Some(
+                DateTime::parse_from_rfc3339(&end_date_str)
+                    .unwrap()
+                    .with_timezone(&Utc),
+            ),
+            None => None,
+        },
+    };
+
+    let note = match arg_matches.get_one::<String>("note") {
+        Some(note) => Some(note.to_string()),
+        None => stmt.read::<Option<String>, _>("note").unwrap(),
+    };
+
+    if let Some(end_date) = end_date {
+        if end_date < start_date {
+            panic!("The end date must not be before the start date");
+        }
+    }
+
+    if schedule_changed {
+        let mut overlap_stmt = db
+            .prepare(
+                "SELECT id FROM missions WHERE id != :id AND start_date < :end_date AND (end_date IS NULL OR end_date > :start_date)",
+            )
+            .unwrap();
+
+        overlap_stmt.bind((":id", id)).unwrap();
+        overlap_stmt
+            .bind((
+                ":end_date",
+                end_date.unwrap_or_else(Utc::now).to_rfc3339().as_str(),
+            ))
+            .unwrap();
+        overlap_stmt
+            .bind((":start_date", start_date.to_rfc3339().as_str()))
+            .unwrap();
+
+        if let Ok(State::Row) = overlap_stmt.next() {
+            panic!("This edit would overlap another mission");
+        }
+    }
+
+    let mut update_stmt = db
+        .prepare(
+            "UPDATE missions SET name = :name, start_date = :start_date, end_date = :end_date, note = :note WHERE id = :id",
+        )
+        .unwrap();
+
+    let end_date_str = end_date.map(|d| d.to_rfc3339());
+
+    update_stmt.bind((":name", name.as_str())).unwrap();
+    update_stmt
+        .bind((":start_date", start_date.to_rfc3339().as_str()))
+        .unwrap();
+    update_stmt
+        .bind((":end_date", end_date_str.as_deref()))
+        .unwrap();
+    update_stmt.bind((":note", note.as_deref())).unwrap();
+    update_stmt.bind((":id", id)).unwrap();
+
+    update_stmt.next().expect("Failed to update the mission");
+
+    println!("Mission {} updated: {}", id, name);
+}
+
 fn resume_latest_mission(db: &Connection) {
     db.execute(
         "UPDATE missions SET end_date = NULL WHERE id IN (SELECT id FROM missions ORDER BY start_date DESC LIMIT 1)",
@@ -233,86 +642,233 @@ fn resume_latest_mission(db: &Connection) {
     .expect("Could not resume the latest mission");
 }
 
-fn print_report(db: &Connection, from: &String) {
-    // TODO: Get a slice of missions for the given range
-    // TODO: Accept strings as date range (e.g last month, yesterday, ...)
-    // let mut stmt = db.prepare("SELECT * FROM missions WHERE start_date");
-
+fn print_report(
+    db: &Connection,
+    config: &Config,
+    from: &str,
+    to: Option<&String>,
+    project: Option<&str>,
+    sort: Option<&str>,
+) {
     let now = Utc::now().timestamp();
-    let tz = timelib::Timezone::parse("UTC").unwrap();
-    let ts = timelib::strtotime(from.as_str(), Some(now), &tz).unwrap();
-    let from_date = DateTime::from_timestamp(ts, 0);
+    let date = parse_time(from);
 
-    if let Some(date) = from_date {
-        if now <= ts {
-            panic!("The date range should start from a past date");
-        }
+    if now <= date.timestamp() {
+        panic!("The date range should start from a past date");
+    }
 
-        let mut stmt = db
-            .prepare("SELECT * FROM missions WHERE start_date >= :start_date OR end_date IS NULL ORDER BY start_date DESC")
-            .unwrap();
+    let to_date = to.map(|to| parse_time(to));
+    let mut missions = fetch_missions(db, Some(date), to_date, project);
+    sort_missions(&mut missions, sort);
 
-        stmt.bind((":start_date", date.to_rfc3339().to_string().as_str()))
-            .unwrap();
+    let mut builder = Builder::default();
+    builder.set_header([
+        "",
+        "Name",
+        "Project",
+        "Started At",
+        "Ended At",
+        "Duration",
+        "Note",
+    ]);
+
+    for mission in &missions {
+        let formatted_end_date = match mission.end_date {
+            Some(date) => config.format_date(date),
+            None => String::new(),
+        };
+
+        builder.push_record([
+            if mission.end_date.is_none() {
+                "⏺"
+            } else {
+                ""
+            },
+            mission.name.as_str(),
+            mission.project.as_deref().unwrap_or(""),
+            config.format_date(mission.start_date).as_str(),
+            formatted_end_date.as_str(),
+            format_elapsed(mission.elapsed_time()).as_str(),
+            mission.note.as_deref().unwrap_or(""),
+        ]);
+    }
 
-        // TODO: Massive duplicate from the list action
-        let mut builder = Builder::default();
-        builder.set_header(["", "Name", "Started At", "Ended At", "Duration"]);
+    if builder.count_rows() > 0 {
+        let mut table = builder.build();
+        table.with(Style::rounded());
+        println!("{}", table);
+    } else {
+        println!("Could not find any missions for the provided time range");
+    }
+}
 
-        while let Ok(State::Row) = stmt.next() {
-            let name = stmt.read::<String, _>("name").unwrap();
-            let start_date = DateTime::parse_from_rfc3339(
-                stmt.read::<String, _>("start_date").unwrap().as_str(),
-            )
-            .unwrap()
-            .with_timezone(&Utc);
-
-            let end_date: Option<DateTime<Utc>> =
-                match stmt.read::<Option<String>, _>("end_date").unwrap() {
-                    Some(end_date_str) => Some(
-                        DateTime::parse_from_rfc3339(&end_date_str)
-                            .unwrap()
-                            .with_timezone(&Utc),
-                    ),
-                    None => None,
-                };
-
-            let mut mission = Mission::new(name, start_date);
-            mission.end_date = end_date;
-
-            let formatted_end_date = match mission.end_date {
-                Some(date) => date.format("%d/%m/%Y %H:%M:%S").to_string(),
-                None => String::new(),
-            };
+// Sums elapsed time across missions in the given range, grouped either by
+// mission name or by calendar day
+fn print_aggregated_report(
+    db: &Connection,
+    from: Option<&String>,
+    to: Option<&String>,
+    by: &str,
+    project: Option<&str>,
+    sort: Option<&str>,
+) {
+    let from_date = from.map(|from| parse_time(from));
+    let to_date = to.map(|to| parse_time(to));
+    let missions = fetch_missions(db, from_date, to_date, project);
+
+    // (key, total duration, earliest start date in the group)
+    let mut groups: Vec<(String, Duration, DateTime<Utc>)> = Vec::new();
+
+    for mission in &missions {
+        let key = match by {
+            "day" => mission.start_date.format("%Y-%m-%d").to_string(),
+            _ => mission.name.clone(),
+        };
 
-            builder.push_record([
-                if end_date.is_none() { "⏺" } else { "" },
-                mission.name.as_str(),
-                mission
-                    .start_date
-                    .format("%d/%m/%Y %H:%M:%S")
-                    .to_string()
-                    .as_str(),
-                formatted_end_date.as_str(),
-                format_duration(mission.elapsed_time().to_std().unwrap())
-                    .to_string()
-                    .as_str(),
-            ]);
+        match groups.iter_mut().find(|(k, _, _)| *k == key) {
+            Some((_, duration, earliest)) => {
+                *duration += mission.elapsed_time();
+                *earliest = (*earliest).min(mission.start_date);
+            }
+            None => groups.push((key, mission.elapsed_time(), mission.start_date)),
         }
+    }
+
+    if groups.is_empty() {
+        println!("Could not find any missions for the provided time range");
+        return;
+    }
 
-        if builder.count_rows() > 0 {
-            let mut table = builder.build();
-            table.with(Style::rounded());
-            println!("{}", table);
+    match sort {
+        Some("duration") => groups.sort_by_key(|g| Reverse(g.1)),
+        Some("start") => groups.sort_by_key(|g| g.2),
+        _ => groups.sort_by_key(|g| g.0.clone()),
+    }
+
+    let total: Duration = groups
+        .iter()
+        .fold(Duration::zero(), |acc, (_, d, _)| acc + *d);
+
+    let mut builder = Builder::default();
+    builder.set_header([
+        if by == "day" { "Day" } else { "Name" },
+        "Duration",
+        "Share",
+    ]);
+
+    for (key, duration, _) in &groups {
+        let share = if total.num_milliseconds() > 0 {
+            duration.num_milliseconds() as f64 / total.num_milliseconds() as f64 * 100.0
         } else {
-            println!("Could not find any missions for the provided time range");
+            0.0
+        };
+
+        builder.push_record([
+            key.as_str(),
+            format_duration(duration.to_std().unwrap())
+                .to_string()
+                .as_str(),
+            format!("{:.1}%", share).as_str(),
+        ]);
+    }
+
+    builder.push_record([
+        "Total",
+        format_duration(total.to_std().unwrap())
+            .to_string()
+            .as_str(),
+        "100.0%",
+    ]);
+
+    let mut table = builder.build();
+    table.with(Style::rounded());
+    println!("{}", table);
+}
+
+// Exports the selected missions to a file or stdout in CSV or JSON format
+fn export_missions(
+    db: &Connection,
+    format: &str,
+    from: Option<&String>,
+    to: Option<&String>,
+    output: Option<&String>,
+) {
+    let from_date = from.map(|from| parse_time(from));
+    let to_date = to.map(|to| parse_time(to));
+    let missions = fetch_missions(db, from_date, to_date, None);
+
+    let contents = match format {
+        "json" => export_to_json(&missions),
+        _ => export_to_csv(&missions),
+    };
+
+    match output {
+        Some(path) => {
+            let mut file = File::create(path).expect("Failed to create the export file");
+            file.write_all(contents.as_bytes())
+                .expect("Failed to write the export file");
         }
+        None => io::stdout()
+            .write_all(contents.as_bytes())
+            .expect("Failed to write to stdout"),
+    }
+}
+
+fn export_to_csv(missions: &[Mission]) -> String {
+    let mut writer = csv::Writer::from_writer(vec![]);
+
+    writer
+        .write_record([
+            "name",
+            "start_date",
+            "end_date",
+            "duration_seconds",
+            "note",
+            "project",
+        ])
+        .unwrap();
+
+    for mission in missions {
+        writer
+            .write_record([
+                mission.name.as_str(),
+                mission.start_date.to_rfc3339().as_str(),
+                mission
+                    .end_date
+                    .map(|d| d.to_rfc3339())
+                    .unwrap_or_default()
+                    .as_str(),
+                mission.elapsed_time().num_seconds().to_string().as_str(),
+                mission.note.as_deref().unwrap_or(""),
+                mission.project.as_deref().unwrap_or(""),
+            ])
+            .unwrap();
     }
+
+    String::from_utf8(writer.into_inner().unwrap()).unwrap()
+}
+
+fn export_to_json(missions: &[Mission]) -> String {
+    let records: Vec<serde_json::Value> = missions
+        .iter()
+        .map(|mission| {
+            let mut value = serde_json::to_value(mission).unwrap();
+            value["duration_seconds"] = mission.elapsed_time().num_seconds().into();
+            value
+        })
+        .collect();
+
+    serde_json::to_string_pretty(&records).unwrap()
 }
 
-fn print_info(db: &Connection) {
+fn print_info(db: &Connection, config: &Config) {
     println!("Database:");
-    println!(" Path: {}", get_db_path());
+    println!(" Path: {}", get_db_path(config));
+    println!();
+
+    println!("Config:");
+    println!(" Date format: {}", config.date_format());
+    println!(" Timezone: {}", config.timezone());
     println!();
 
     // TODO: Use count(*)
@@ -340,3 +896,71 @@ fn print_info(db: &Connection) {
 
     println!(" Finished: {}", finished);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_elapsed_renders_hours_and_minutes() {
+        assert_eq!(
+            format_elapsed(Duration::seconds(2 * 3600 + 15 * 60)),
+            "2h15m"
+        );
+    }
+
+    #[test]
+    fn format_elapsed_drops_zero_minutes() {
+        assert_eq!(format_elapsed(Duration::hours(3)), "3h");
+    }
+
+    #[test]
+    fn format_elapsed_renders_minutes_and_seconds() {
+        assert_eq!(format_elapsed(Duration::seconds(5 * 60 + 9)), "5m9s");
+    }
+
+    #[test]
+    fn format_elapsed_renders_sub_second_precision() {
+        assert_eq!(format_elapsed(Duration::milliseconds(47_120)), "47.120s");
+    }
+
+    #[test]
+    fn format_elapsed_drops_zero_millis() {
+        assert_eq!(format_elapsed(Duration::seconds(8)), "8s");
+    }
+
+    #[test]
+    fn parse_name_tag_extracts_trailing_tag() {
+        assert_eq!(
+            parse_name_tag("Refactor @client-x"),
+            ("Refactor".to_string(), Some("client-x".to_string()))
+        );
+    }
+
+    #[test]
+    fn parse_name_tag_extracts_tag_mid_string() {
+        assert_eq!(
+            parse_name_tag("Refactor @client-x the parser"),
+            (
+                "Refactor the parser".to_string(),
+                Some("client-x".to_string())
+            )
+        );
+    }
+
+    #[test]
+    fn parse_name_tag_without_tag() {
+        assert_eq!(
+            parse_name_tag("Refactor the parser"),
+            ("Refactor the parser".to_string(), None)
+        );
+    }
+
+    #[test]
+    fn parse_name_tag_ignores_bare_at() {
+        assert_eq!(
+            parse_name_tag("Refactor @ the parser"),
+            ("Refactor @ the parser".to_string(), None)
+        );
+    }
+}